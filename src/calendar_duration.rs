@@ -1,5 +1,8 @@
 use std::cmp::min;
+use std::error::Error;
+use std::fmt;
 use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
 
 use chrono::{Datelike, DateTime, Duration as OldDuration, NaiveDate, NaiveDateTime, TimeZone};
 
@@ -24,6 +27,29 @@ pub struct CalendarDuration {
     duration: OldDuration,
     months: i32,
     years: i32,
+    overflow: MonthOverflow,
+}
+
+/// What to do when adding months lands on a day that doesn't exist in the
+/// target month, e.g. adding one month to January 31st.
+///
+/// The years part of a `CalendarDuration` does not use this policy: shifting
+/// February 29th into a non-leap year always yields `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MonthOverflow {
+    /// Clamp to the last day of the target month: Jan 31 + 1 month -> Feb 28. This is
+    /// `CalendarDuration`'s original, and default, behavior.
+    Clamp,
+    /// Roll the surplus days over into the following month: Jan 31 + 1 month -> Mar 3.
+    Rollover,
+    /// Fail (return `None`) rather than adjust the day: Jan 31 + 1 month -> `None`.
+    Strict,
+}
+
+impl Default for MonthOverflow {
+    fn default() -> Self {
+        MonthOverflow::Clamp
+    }
 }
 
 impl CalendarDuration {
@@ -32,6 +58,7 @@ impl CalendarDuration {
             duration: OldDuration::zero(),
             months: 0,
             years: years,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -40,6 +67,7 @@ impl CalendarDuration {
             duration: OldDuration::zero(),
             months: months,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -48,6 +76,7 @@ impl CalendarDuration {
             duration: OldDuration::weeks(weeks),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -56,6 +85,7 @@ impl CalendarDuration {
             duration: OldDuration::days(days),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -64,6 +94,7 @@ impl CalendarDuration {
             duration: OldDuration::hours(hours),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -72,6 +103,7 @@ impl CalendarDuration {
             duration: OldDuration::minutes(minutes),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -80,6 +112,7 @@ impl CalendarDuration {
             duration: OldDuration::seconds(seconds),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -88,6 +121,7 @@ impl CalendarDuration {
             duration: OldDuration::milliseconds(milliseconds),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -96,6 +130,7 @@ impl CalendarDuration {
             duration: OldDuration::microseconds(microseconds),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -104,6 +139,7 @@ impl CalendarDuration {
             duration: OldDuration::nanoseconds(nanoseconds),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -112,6 +148,7 @@ impl CalendarDuration {
             duration: OldDuration::zero(),
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 
@@ -119,53 +156,152 @@ impl CalendarDuration {
         &self.duration
     }
 
+    /// the policy used to resolve a month shift that lands on a day which
+    /// doesn't exist in the target month
+    pub fn overflow(&self) -> MonthOverflow {
+        self.overflow
+    }
+
+    /// returns a copy of `self` with the given month-end overflow policy, so a single
+    /// `CalendarDuration` (and the iterator built from it) applies one consistent rule
+    /// across all of its steps.
+    pub fn with_overflow(mut self, overflow: MonthOverflow) -> CalendarDuration {
+        self.overflow = overflow;
+        self
+    }
+
     pub fn checked_add(&self, other: &Self) -> Option<Self> {
         Some(CalendarDuration {
                  duration: try_opt!(self.duration.checked_add(&other.duration)),
                  months: try_opt!(self.months.checked_add(other.months)),
                  years: try_opt!(self.years.checked_add(other.years)),
+                 overflow: self.overflow,
              })
     }
 
-    //TODO: Implement checked_mul once there is a new chrono::Duration type
-    // pub fn checked_mul(&self, factor: i32) -> Option<CalendarDuration> {
-    //     Some(CalendarDuration {
-    //         duration: try_opt!(self.duration.checked_mut(factor)),
-    //         months: try_opt!(self.months.checked_mul(factor)),
-    //         years: try_opt!(self.years.checked_mul(factor)),
-    //     })
-    // }
+    /// Like `&CalendarDuration * i32`, but returns `None` on overflow instead of
+    /// panicking, so a long-running `OpenEndedDateIterator` can terminate cleanly
+    /// instead of panicking deep inside chrono once `iterations` gets large.
+    pub fn checked_mul(&self, factor: i32) -> Option<CalendarDuration> {
+        Some(CalendarDuration {
+                 duration: try_opt!(self.duration.checked_mul(factor)),
+                 months: try_opt!(self.months.checked_mul(factor)),
+                 years: try_opt!(self.years.checked_mul(factor)),
+                 overflow: self.overflow,
+             })
+    }
+
+    /// whether this duration is a no-op step, i.e. adding it never changes the date.
+    pub fn is_zero(&self) -> bool {
+        self.duration == OldDuration::zero() && self.months == 0 && self.years == 0
+    }
+
+    /// A rough estimate of this duration's length in nanoseconds, treating a month
+    /// as its average (Gregorian) length and a year as 365.2425 days. Calendar steps
+    /// don't have a fixed length, so this is only good for *estimating* where a date
+    /// close to a given target lands, e.g. to seed a search that then confirms the
+    /// exact answer with `checked_add`.
+    pub fn approx_nanos(&self) -> i64 {
+        const NANOS_PER_DAY: f64 = 86_400_000_000_000.0;
+        const AVG_MONTH_DAYS: f64 = 30.436_875;
+        const AVG_YEAR_DAYS: f64 = 365.2425;
+
+        let calendar_days = self.years as f64 * AVG_YEAR_DAYS + self.months as f64 * AVG_MONTH_DAYS;
+        let fixed_nanos = self.duration.num_nanoseconds().unwrap_or(i64::max_value());
+        (calendar_days * NANOS_PER_DAY) as i64 + fixed_nanos
+    }
 }
 
 pub fn add_years<Tz: TimeZone>(dt: &DateTime<Tz>, years: i32) -> Option<DateTime<Tz>> {
     dt.with_year(try_opt!(dt.year().checked_add(years)))
 }
 
-pub fn add_months_naive_date(date: &NaiveDate, months: i32) -> Option<NaiveDate> {
-    let next_month_0 = try_opt!((date.month0() as i64).checked_add(months as i64));
-    let additional_years = next_month_0 / 12;
-    let next_month_0 = (next_month_0 % 12) as u32;
-    let additional_years = if additional_years >= (i32::max_value() as i64) {
+pub fn add_years_naive_dt(dt: &NaiveDateTime, years: i32) -> Option<NaiveDateTime> {
+    dt.with_year(try_opt!(dt.year().checked_add(years)))
+}
+
+/// Adds `months` to `date`, resolving a target day that doesn't exist (e.g. January
+/// 31st + 1 month) according to `overflow`.
+pub fn add_months_naive_date(date: &NaiveDate,
+                             months: i32,
+                             overflow: MonthOverflow)
+                             -> Option<NaiveDate> {
+    let total_months = try_opt!((date.month0() as i64).checked_add(months as i64));
+    //`/` and `%` truncate toward zero, which turns a negative `total_months` into a
+    //negative `next_month_0` instead of borrowing a year; `div_euclid`/`rem_euclid`
+    //always return a `next_month_0` in `0..12`, e.g. -1 -> (additional_years: -1, next_month_0: 11).
+    let additional_years = total_months.div_euclid(12);
+    let next_month_0 = total_months.rem_euclid(12) as u32;
+    if additional_years > i32::max_value() as i64 || additional_years < i32::min_value() as i64 {
         return None;
-    } else {
-        additional_years as i32
-    };
-    let next_year = try_opt!(date.year().checked_add(additional_years));
-    let next_day = min(date.day(), last_day_of_month_0(next_year, next_month_0));
-    NaiveDate::from_ymd_opt(next_year, next_month_0 + 1, next_day)
+    }
+    let next_year = try_opt!(date.year().checked_add(additional_years as i32));
+    let last_day = last_day_of_month_0(next_year, next_month_0);
+
+    match overflow {
+        MonthOverflow::Clamp => {
+            NaiveDate::from_ymd_opt(next_year, next_month_0 + 1, min(date.day(), last_day))
+        }
+        MonthOverflow::Strict => NaiveDate::from_ymd_opt(next_year, next_month_0 + 1, date.day()),
+        MonthOverflow::Rollover => {
+            let surplus_days = date.day().saturating_sub(last_day);
+            NaiveDate::from_ymd_opt(next_year, next_month_0 + 1, date.day() - surplus_days)
+                .map(|next_date| next_date + OldDuration::days(surplus_days as i64))
+        }
+    }
 }
 
-pub fn add_months_naive_dt(dt: &NaiveDateTime, months: i32) -> Option<NaiveDateTime> {
-    add_months_naive_date(&dt.date(), months).map(|date| NaiveDateTime::new(date, dt.time()))
+pub fn add_months_naive_dt(dt: &NaiveDateTime,
+                           months: i32,
+                           overflow: MonthOverflow)
+                           -> Option<NaiveDateTime> {
+    add_months_naive_date(&dt.date(), months, overflow)
+        .map(|date| NaiveDateTime::new(date, dt.time()))
 }
 
-pub fn add_months_dt<Tz: TimeZone>(dt: &DateTime<Tz>, months: i32) -> Option<DateTime<Tz>> {
-    add_months_naive_dt(&dt.naive_utc(), months).map(|naive| {
+/// Adds months to `dt` by shifting its UTC instant and reattaching the original offset.
+/// For a zoned `dt` that crosses a DST transition this silently shifts the local
+/// wall-clock time, and the reattached offset may not even be valid for the new date.
+/// Use `add_months_dt_local` if you want "09:00 + 1 month" to stay "09:00 local".
+pub fn add_months_dt<Tz: TimeZone>(dt: &DateTime<Tz>,
+                                   months: i32,
+                                   overflow: MonthOverflow)
+                                   -> Option<DateTime<Tz>> {
+    add_months_naive_dt(&dt.naive_utc(), months, overflow).map(|naive| {
                                                          DateTime::from_utc(naive,
                                                                             dt.offset().clone())
                                                      })
 }
 
+/// Re-resolves a naive local date/time back into a zoned `DateTime`, for the
+/// `_local` family of functions below. Returns `None` when the local time is
+/// nonexistent (falls in a DST gap) or ambiguous (falls in a DST overlap) rather
+/// than guessing which offset was meant.
+fn relocalize<Tz: TimeZone>(tz: &Tz, naive: NaiveDateTime) -> Option<DateTime<Tz>> {
+    tz.from_local_datetime(&naive).single()
+}
+
+/// Like `add_years`, but operates on `dt`'s local wall-clock time and re-resolves it
+/// through the time zone instead of keeping the original offset, so a DST gap/overlap
+/// introduced by the shift is reported as `None` rather than silently producing an
+/// invalid or ambiguous local time.
+pub fn add_years_dt_local<Tz: TimeZone>(dt: &DateTime<Tz>, years: i32) -> Option<DateTime<Tz>> {
+    let naive = try_opt!(add_years_naive_dt(&dt.naive_local(), years));
+    relocalize(&dt.timezone(), naive)
+}
+
+/// Like `add_months_dt`, but computes on `dt`'s local wall-clock time (`naive_local`)
+/// and re-resolves it through the time zone, so "09:00 + 1 month" stays "09:00 local"
+/// instead of drifting by the zone's UTC offset change across the DST transition.
+/// Returns `None` when the shifted local time is nonexistent or ambiguous.
+pub fn add_months_dt_local<Tz: TimeZone>(dt: &DateTime<Tz>,
+                                         months: i32,
+                                         overflow: MonthOverflow)
+                                         -> Option<DateTime<Tz>> {
+    let naive = try_opt!(add_months_naive_dt(&dt.naive_local(), months, overflow));
+    relocalize(&dt.timezone(), naive)
+}
+
 /// Add the `CalendarDuration` to given dt, returning None on overflow.
 /// Note that adding e.g. one month to January 30th will return February 28th.
 /// See `CalendarDuration` for more details.
@@ -181,7 +317,21 @@ pub fn checked_add<Tz: TimeZone>(dt: &DateTime<Tz>,
     dt.clone()
         .checked_add_signed(duration.duration)
         .and_then(|dt| add_years(&dt, duration.years))
-        .and_then(|dt| add_months_dt(&dt, duration.months))
+        .and_then(|dt| add_months_dt(&dt, duration.months, duration.overflow))
+}
+
+/// Like `checked_add`, but carries out the whole shift (seconds, years, months) on
+/// `dt`'s local wall-clock time and only re-resolves the zone once, at the end. This
+/// keeps "09:00 + 1 month" at "09:00 local" across a DST transition; see
+/// `add_months_dt_local` for why the UTC-based `checked_add` can't make that promise.
+/// Returns `None` on overflow, or if the shifted local time is nonexistent/ambiguous.
+pub fn checked_add_local<Tz: TimeZone>(dt: &DateTime<Tz>,
+                                       duration: &CalendarDuration)
+                                       -> Option<DateTime<Tz>> {
+    let naive = try_opt!(dt.naive_local().checked_add_signed(duration.duration));
+    let naive = try_opt!(add_years_naive_dt(&naive, duration.years));
+    let naive = try_opt!(add_months_naive_dt(&naive, duration.months, duration.overflow));
+    relocalize(&dt.timezone(), naive)
 }
 
 /// As this crate does not define `DateTime`, it cannot implement `Add`. Hence this free function.
@@ -195,6 +345,7 @@ impl From<OldDuration> for CalendarDuration {
             duration: duration,
             months: 0,
             years: 0,
+            overflow: MonthOverflow::default(),
         }
     }
 }
@@ -202,11 +353,13 @@ impl From<OldDuration> for CalendarDuration {
 impl Add for CalendarDuration {
     type Output = CalendarDuration;
 
+    /// the overflow policy of the left-hand side wins, just like `checked_add`
     fn add(self, rhs: CalendarDuration) -> CalendarDuration {
         CalendarDuration {
             duration: self.duration + rhs.duration,
             months: self.months + rhs.months,
             years: self.years + rhs.years,
+            overflow: self.overflow,
         }
     }
 }
@@ -214,11 +367,13 @@ impl Add for CalendarDuration {
 impl Sub for CalendarDuration {
     type Output = CalendarDuration;
 
+    /// the overflow policy of the left-hand side wins, just like `checked_add`
     fn sub(self, rhs: CalendarDuration) -> CalendarDuration {
         CalendarDuration {
             duration: self.duration - rhs.duration,
             months: self.months - rhs.months,
             years: self.years - rhs.years,
+            overflow: self.overflow,
         }
     }
 }
@@ -232,6 +387,7 @@ impl<'a> Mul<i32> for &'a CalendarDuration {
             duration: self.duration * rhs,
             months: self.months * rhs,
             years: self.years * rhs,
+            overflow: self.overflow,
         }
     }
 }
@@ -244,6 +400,7 @@ impl Div<i32> for CalendarDuration {
             duration: self.duration / rhs,
             months: self.months / rhs,
             years: self.years / rhs,
+            overflow: self.overflow,
         }
     }
 }
@@ -256,8 +413,212 @@ impl Neg for CalendarDuration {
             duration: -self.duration,
             months: -self.months,
             years: -self.years,
+            overflow: self.overflow,
+        }
+    }
+}
+
+/// Error returned by `CalendarDuration::from_str` when a string is not a valid
+/// ISO 8601 duration (`P[n]Y[n]M[n]W[n]DT[n]H[n]M[n]S`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseCalendarDurationError {
+    message: String,
+}
+
+impl ParseCalendarDurationError {
+    fn new<S: Into<String>>(message: S) -> Self {
+        ParseCalendarDurationError { message: message.into() }
+    }
+}
+
+impl fmt::Display for ParseCalendarDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid ISO 8601 duration: {}", self.message)
+    }
+}
+
+impl Error for ParseCalendarDurationError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// splits e.g. `"3Y2M5D"` into `[(3.0, 'Y'), (2.0, 'M'), (5.0, 'D')]`.
+fn tokenize(part: &str) -> Result<Vec<(f64, char)>, ParseCalendarDurationError> {
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+    for c in part.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+        } else {
+            if number.is_empty() {
+                return Err(ParseCalendarDurationError::new(format!("expected a number before '{}'", c)));
+            }
+            let value = try!(number.parse::<f64>()
+                .map_err(|_| ParseCalendarDurationError::new(format!("'{}' is not a number", number))));
+            tokens.push((value, c));
+            number.clear();
         }
     }
+    if !number.is_empty() {
+        return Err(ParseCalendarDurationError::new(format!("'{}' is missing a designator", number)));
+    }
+    Ok(tokens)
+}
+
+/// parses the designators of one part (date or time) against their fixed ISO 8601 order,
+/// folding each one into `duration` via `to_calendar_duration`.
+fn parse_part<F>(part: &str,
+                  order: &[char],
+                  to_calendar_duration: F)
+                  -> Result<CalendarDuration, ParseCalendarDurationError>
+    where F: Fn(char, f64) -> Result<CalendarDuration, ParseCalendarDurationError>
+{
+    let mut duration = CalendarDuration::zero();
+    let mut min_pos = 0;
+    for (value, designator) in try!(tokenize(part)) {
+        let pos = try!(order.iter()
+            .position(|&c| c == designator)
+            .ok_or_else(|| ParseCalendarDurationError::new(format!("unexpected designator '{}'", designator))));
+        if pos < min_pos {
+            return Err(ParseCalendarDurationError::new(format!("designator '{}' is out of order", designator)));
+        }
+        min_pos = pos + 1;
+        duration = duration + try!(to_calendar_duration(designator, value));
+    }
+    Ok(duration)
+}
+
+/// Errors out on a fractional `value`: only the smallest designator of a duration
+/// (seconds) may have a fractional part per ISO 8601, and e.g. `P1.5Y` has no
+/// single well-defined number of days to add the fraction as.
+fn reject_fraction(designator: char, value: f64) -> Result<(), ParseCalendarDurationError> {
+    if value.fract() != 0.0 {
+        return Err(ParseCalendarDurationError::new(format!("'{}' does not support a fractional value; \
+                                                              only seconds may have one",
+                                                             designator)));
+    }
+    Ok(())
+}
+
+impl FromStr for CalendarDuration {
+    type Err = ParseCalendarDurationError;
+
+    /// Parses the ISO 8601 duration grammar `P[n]Y[n]M[n]W[n]DT[n]H[n]M[n]S`.
+    ///
+    /// Note the ambiguity this resolves: `M` means months before the `T` separator and
+    /// minutes after it. Fractional seconds (`PT1.5S`) become nanoseconds; every other
+    /// designator is rejected if given a fractional value (e.g. `P1.5Y`), since there's no
+    /// single well-defined number of days a fractional year or month adds up to. `P` alone,
+    /// or a string missing the leading `P`, is rejected rather than treated as a zero duration.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with('P') {
+            return Err(ParseCalendarDurationError::new("missing leading 'P'"));
+        }
+        let rest = &s[1..];
+
+        let (date_part, time_part) = match rest.find('T') {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+
+        if date_part.is_empty() && time_part.map_or(true, |t| t.is_empty()) {
+            return Err(ParseCalendarDurationError::new("empty duration"));
+        }
+
+        let date_duration = try!(parse_part(date_part, &['Y', 'M', 'W', 'D'], |designator, value| {
+            try!(reject_fraction(designator, value));
+            Ok(match designator {
+                'Y' => CalendarDuration::years(value as i32),
+                'M' => CalendarDuration::months(value as i32),
+                'W' => CalendarDuration::weeks(value as i64),
+                _ => CalendarDuration::days(value as i64),
+            })
+        }));
+
+        let time_duration = match time_part {
+            Some(time_part) => {
+                try!(parse_part(time_part, &['H', 'M', 'S'], |designator, value| {
+                    Ok(match designator {
+                        'H' => {
+                            try!(reject_fraction(designator, value));
+                            CalendarDuration::hours(value as i64)
+                        }
+                        'M' => {
+                            try!(reject_fraction(designator, value));
+                            CalendarDuration::minutes(value as i64)
+                        }
+                        _ => {
+                            let nanos = (value.fract() * 1_000_000_000f64).round() as i64;
+                            CalendarDuration::seconds(value.trunc() as i64) +
+                            CalendarDuration::nanoseconds(nanos)
+                        }
+                    })
+                }))
+            }
+            None => CalendarDuration::zero(),
+        };
+
+        Ok(date_duration + time_duration)
+    }
+}
+
+impl fmt::Display for CalendarDuration {
+    /// Formats as the canonical ISO 8601 duration, skipping zero components (but emitting
+    /// `PT0S` for a zero duration). Note that `CalendarDuration` does not retain whether a
+    /// duration was built from `weeks()` or `days()` once combined, so this always emits
+    /// whole days rather than an equivalent number of weeks.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "P"));
+
+        let mut wrote_date_part = false;
+        if self.years != 0 {
+            try!(write!(f, "{}Y", self.years));
+            wrote_date_part = true;
+        }
+        if self.months != 0 {
+            try!(write!(f, "{}M", self.months));
+            wrote_date_part = true;
+        }
+
+        let mut remainder = self.duration;
+        let days = remainder.num_days();
+        remainder = remainder - OldDuration::days(days);
+        if days != 0 {
+            try!(write!(f, "{}D", days));
+            wrote_date_part = true;
+        }
+
+        let hours = remainder.num_hours();
+        remainder = remainder - OldDuration::hours(hours);
+        let minutes = remainder.num_minutes();
+        remainder = remainder - OldDuration::minutes(minutes);
+        let seconds = remainder.num_seconds();
+        remainder = remainder - OldDuration::seconds(seconds);
+        let nanos = remainder.num_nanoseconds().unwrap_or(0);
+
+        if hours != 0 || minutes != 0 || seconds != 0 || nanos != 0 {
+            try!(write!(f, "T"));
+            if hours != 0 {
+                try!(write!(f, "{}H", hours));
+            }
+            if minutes != 0 {
+                try!(write!(f, "{}M", minutes));
+            }
+            if seconds != 0 || nanos != 0 {
+                if nanos != 0 {
+                    let fraction = format!("{:09}", nanos.abs() as u64);
+                    try!(write!(f, "{}.{}S", seconds, fraction.trim_end_matches('0')));
+                } else {
+                    try!(write!(f, "{}S", seconds));
+                }
+            }
+        } else if !wrote_date_part {
+            try!(write!(f, "T0S"));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -305,6 +666,15 @@ mod tests {
         assert_eq!(None, checked_add(&dt, &duration));
     }
 
+    #[test]
+    pub fn checked_mul_overflows_to_none() {
+        let duration = CalendarDuration::years(i32::max_value());
+
+        assert_eq!(None, duration.checked_mul(2));
+        assert_eq!(Some(CalendarDuration::years(i32::max_value())),
+                   duration.checked_mul(1));
+    }
+
     #[test]
     pub fn add_adjusted() {
         let input = "1996-12-31T16:39:57.123Z";
@@ -326,4 +696,179 @@ mod tests {
         //But May is ok
         assert_eq!("1997-05-31T16:39:57.123Z", format!("{:?}", result));
     }
+
+    #[test]
+    pub fn add_rollover_overflow() {
+        let input = "1996-12-31T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let duration = CalendarDuration::months(2).with_overflow(MonthOverflow::Rollover);
+        let result = add(&dt, &duration);
+        //February only has 28 days in 1997, so the 3 surplus days roll into March
+        assert_eq!("1997-03-03T16:39:57.123Z", format!("{:?}", result));
+    }
+
+    #[test]
+    pub fn add_strict_overflow() {
+        let input = "1996-12-31T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let duration = CalendarDuration::months(2).with_overflow(MonthOverflow::Strict);
+        assert_eq!(None, checked_add(&dt, &duration));
+
+        //March has 31 days, so adding 3 months from December 31st isn't ambiguous
+        let duration = CalendarDuration::months(3).with_overflow(MonthOverflow::Strict);
+        let result = add(&dt, &duration);
+        assert_eq!("1997-03-31T16:39:57.123Z", format!("{:?}", result));
+    }
+
+    #[test]
+    pub fn add_negative_months_crosses_year_boundary_backward() {
+        let input = "2006-01-15T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let result = add(&dt, &CalendarDuration::months(-1));
+        assert_eq!("2005-12-15T16:39:57.123Z", format!("{:?}", result));
+
+        //several years back, still lands on the right month/year pair
+        let result = add(&dt, &CalendarDuration::months(-13));
+        assert_eq!("2004-12-15T16:39:57.123Z", format!("{:?}", result));
+    }
+
+    #[test]
+    pub fn with_overflow_is_carried_through_combination() {
+        let duration = CalendarDuration::months(2).with_overflow(MonthOverflow::Rollover) +
+                       CalendarDuration::days(1);
+        assert_eq!(MonthOverflow::Rollover, duration.overflow());
+    }
+
+    #[test]
+    pub fn parse_date_and_time_components() {
+        let duration = CalendarDuration::from_str("P3Y1M2DT4H5M6S").unwrap();
+        let expected = CalendarDuration::years(3) + CalendarDuration::months(1) +
+                       CalendarDuration::days(2) +
+                       CalendarDuration::hours(4) +
+                       CalendarDuration::minutes(5) +
+                       CalendarDuration::seconds(6);
+        assert_eq!(expected, duration);
+    }
+
+    #[test]
+    pub fn parse_disambiguates_month_from_minute() {
+        //'M' means months before 'T', minutes after
+        let duration = CalendarDuration::from_str("P1MT1M").unwrap();
+        let expected = CalendarDuration::months(1) + CalendarDuration::minutes(1);
+        assert_eq!(expected, duration);
+    }
+
+    #[test]
+    pub fn parse_weeks_and_fractional_seconds() {
+        let duration = CalendarDuration::from_str("P2WT1.5S").unwrap();
+        let expected = CalendarDuration::weeks(2) + CalendarDuration::seconds(1) +
+                       CalendarDuration::nanoseconds(500_000_000);
+        assert_eq!(expected, duration);
+    }
+
+    #[test]
+    pub fn parse_rejects_missing_p() {
+        assert!(CalendarDuration::from_str("1Y").is_err());
+    }
+
+    #[test]
+    pub fn parse_rejects_empty_duration() {
+        assert!(CalendarDuration::from_str("P").is_err());
+    }
+
+    #[test]
+    pub fn parse_rejects_out_of_order_designators() {
+        assert!(CalendarDuration::from_str("P1D1Y").is_err());
+    }
+
+    #[test]
+    pub fn parse_rejects_fractional_years_months_weeks_days() {
+        assert!(CalendarDuration::from_str("P1.5Y").is_err());
+        assert!(CalendarDuration::from_str("P1.5M").is_err());
+        assert!(CalendarDuration::from_str("P1.5W").is_err());
+        assert!(CalendarDuration::from_str("P1.5D").is_err());
+    }
+
+    #[test]
+    pub fn parse_rejects_fractional_hours_and_minutes() {
+        assert!(CalendarDuration::from_str("PT1.5H").is_err());
+        assert!(CalendarDuration::from_str("PT1.5M").is_err());
+    }
+
+    #[test]
+    pub fn display_skips_zero_components() {
+        let duration = CalendarDuration::years(1) + CalendarDuration::minutes(30);
+        assert_eq!("P1YT30M", duration.to_string());
+    }
+
+    #[test]
+    pub fn display_zero_duration() {
+        assert_eq!("PT0S", CalendarDuration::zero().to_string());
+    }
+
+    #[test]
+    pub fn display_fractional_seconds() {
+        let duration = CalendarDuration::seconds(1) + CalendarDuration::nanoseconds(500_000_000);
+        assert_eq!("PT1.5S", duration.to_string());
+    }
+
+    #[test]
+    pub fn checked_add_local_preserves_wall_clock_time() {
+        let input = "1996-12-19T09:00:00Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let duration = CalendarDuration::months(2) + CalendarDuration::hours(1);
+        let result = checked_add_local(&dt, &duration).unwrap();
+
+        //09:00 local + 2 months 1 hour lands on 10:00 local (no DST to cross under `Utc`, but the
+        //local-time arithmetic should agree with the UTC-based result in that case)
+        assert_eq!(checked_add(&dt, &duration), Some(result));
+        assert_eq!("1997-02-19T10:00:00Z", format!("{:?}", result));
+    }
+
+    #[test]
+    pub fn add_months_dt_local_agrees_with_add_months_dt_without_dst() {
+        let input = "1996-12-31T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        assert_eq!(add_months_dt(&dt, 2, MonthOverflow::Clamp),
+                   add_months_dt_local(&dt, 2, MonthOverflow::Clamp));
+    }
+
+    #[test]
+    pub fn add_months_dt_local_agrees_with_add_months_dt_for_negative_months_across_year() {
+        let input = "2006-01-15T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        assert_eq!(add_months_dt(&dt, -1, MonthOverflow::Clamp),
+                   add_months_dt_local(&dt, -1, MonthOverflow::Clamp));
+        assert_eq!(Some("2005-12-15T16:39:57.123Z".to_string()),
+                   add_months_dt_local(&dt, -1, MonthOverflow::Clamp).map(|d| format!("{:?}", d)));
+    }
+
+    #[test]
+    pub fn checked_add_local_handles_negative_months_across_year() {
+        let input = "2006-01-15T09:00:00Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let duration = CalendarDuration::months(-1);
+        let result = checked_add_local(&dt, &duration).unwrap();
+
+        assert_eq!(checked_add(&dt, &duration), Some(result));
+        assert_eq!("2005-12-15T09:00:00Z", format!("{:?}", result));
+    }
+
+    #[test]
+    pub fn display_round_trips_through_parsing() {
+        let duration = CalendarDuration::years(3) + CalendarDuration::months(1) +
+                       CalendarDuration::days(2) +
+                       CalendarDuration::hours(4) +
+                       CalendarDuration::minutes(5) +
+                       CalendarDuration::seconds(6);
+        let formatted = duration.to_string();
+        assert_eq!(duration, CalendarDuration::from_str(&formatted).unwrap());
+    }
 }
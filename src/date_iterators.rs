@@ -1,4 +1,4 @@
-use chrono::{DateTime, TimeZone};
+use chrono::{DateTime, Datelike, Duration as OldDuration, TimeZone, Weekday};
 
 use calendar_duration::{CalendarDuration, checked_add};
 
@@ -13,14 +13,25 @@ pub struct OpenEndedDateIterator<Tz: TimeZone> {
 }
 
 impl<Tz: TimeZone> OpenEndedDateIterator<Tz> {
-    pub fn to(self, to: DateTime<Tz>) -> ClosedDateIterator<Tz, Self> {
+    pub fn to(self, to: DateTime<Tz>) -> ClosedDateIterator<Tz> {
         date_iterator_to(self, to)
     }
 
     /// needed here so that pairwise can work
     fn current(&self) -> Option<DateTime<Tz>> {
-        //TODO: The multiplication should be checked_mul as well but we'll wait for a better `Duration` type for that...
-        checked_add(&self.from, &(&self.duration * self.iterations))
+        self.at(self.iterations)
+    }
+
+    /// `from + n * duration`, for an arbitrary (possibly negative) `n`.
+    ///
+    /// `current()` is just `self.at(self.iterations)`; exposing this separately lets
+    /// `ClosedDateIterator`/`ClosedPairwiseDateIterator` probe the grid from the `to` end
+    /// without needing to step an iterator one-by-one to get there.
+    ///
+    /// Uses `checked_mul` so a large `n` overflows into `None` (terminating the
+    /// iterator) instead of panicking inside chrono.
+    fn at(&self, n: i32) -> Option<DateTime<Tz>> {
+        checked_add(&self.from, &try_opt!(self.duration.checked_mul(n)))
     }
 
     /// returns a pairwise iterator of (next, after_next) dates. This is if you use the date iterator to
@@ -35,6 +46,29 @@ impl<Tz: TimeZone> OpenEndedDateIterator<Tz> {
     pub fn pairwise(self) -> OpenEndedPairwiseDateIterator<Tz> {
         OpenEndedPairwiseDateIterator { iter: self }
     }
+
+    /// snaps `from` to the next occurrence of `weekday` (staying put if `from` is
+    /// already on that weekday) and resets the step count, so the first date this
+    /// iterator yields afterwards falls on `weekday`. This mirrors chrono's
+    /// Monday-based ISO week model (`NaiveWeek`/`IsoWeek`), and is meant for
+    /// week-stepping durations, e.g. `date_iterator_from(dt, CalendarDuration::weeks(2))
+    /// .aligned_to(Weekday::Mon)` for a fortnightly schedule that always lands on Monday.
+    ///
+    /// Only the snap to `weekday` is applied here; stepping afterwards still adds the
+    /// *entire* `duration`, not just a whole-week part of it (`CalendarDuration` has no
+    /// such part to step by in isolation - a `weeks(n)` duration is just `n*7` days under
+    /// the hood, indistinguishable from e.g. `days(n*7)`). So the weekday alignment this
+    /// sets up is only preserved by durations that are a whole number of weeks; a duration
+    /// with a day/month/year part (e.g. `weeks(2) + days(1)`) will drift off `weekday` from
+    /// the second yielded date onward.
+    pub fn aligned_to(mut self, weekday: Weekday) -> OpenEndedDateIterator<Tz> {
+        let diff = weekday.num_days_from_monday() as i64 -
+                   self.from.weekday().num_days_from_monday() as i64;
+        let days_ahead = ((diff % 7) + 7) % 7;
+        self.from = self.from + OldDuration::days(days_ahead);
+        self.iterations = 0;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -46,24 +80,125 @@ pub struct OpenEndedPairwiseDateIterator<Tz: TimeZone> {
 /// TODO: Find a better name :)
 /// TODO: Once impl Trait is stable, get rid of this struct and use `iterator.take_while()`
 #[derive(Debug)]
-pub struct ClosedDateIterator<Tz: TimeZone, Iter: Iterator<Item = DateTime<Tz>>> {
-    iter: Iter,
+pub struct ClosedDateIterator<Tz: TimeZone> {
+    iter: OpenEndedDateIterator<Tz>,
     to: DateTime<Tz>,
+    /// Iteration index of the last element not yet yielded from the back, lazily
+    /// computed by `next_back()` on first use (`None` means "not searched yet").
+    back: Option<i32>,
 }
 
-impl<Tz: TimeZone> ClosedDateIterator<Tz, OpenEndedDateIterator<Tz>> {
+impl<Tz: TimeZone> ClosedDateIterator<Tz> {
     pub fn pairwise(self) -> ClosedPairwiseDateIterator<Tz> {
         ClosedPairwiseDateIterator {
             iter: self.iter.pairwise(),
             to: self.to,
+            back: None,
+        }
+    }
+
+    /// shared by `ExactSizeIterator::len` and `Iterator::size_hint`. `date_iterator_to`
+    /// rejects a zero-duration step at construction, so this is always well-defined.
+    fn exact_len(&self) -> usize {
+        count_in_range(self.iter.iterations,
+                        &self.to,
+                        self.iter.duration.approx_nanos(),
+                        |n| self.iter.at(n))
+    }
+}
+
+/// find the largest iteration index `n` (starting no lower than `from`) such that
+/// `grid.at(n) < to`, walking the grid one step at a time.
+///
+/// Used to seed `back` on the first call to `next_back()`. This walks the range rather
+/// than computing it in closed form: simple and correct, not the fastest possible.
+fn last_in_range<Tz: TimeZone>(grid: &OpenEndedDateIterator<Tz>,
+                               to: &DateTime<Tz>,
+                               from: i32)
+                               -> Option<i32> {
+    let mut last = None;
+    let mut n = from;
+    while let Some(dt) = grid.at(n) {
+        if dt < *to {
+            last = Some(n);
+            n += 1;
+        } else {
+            break;
         }
     }
+    last
 }
 
 #[derive(Debug)]
 pub struct ClosedPairwiseDateIterator<Tz: TimeZone> {
     iter: OpenEndedPairwiseDateIterator<Tz>,
     to: DateTime<Tz>,
+    /// see `ClosedDateIterator::back`
+    back: Option<i32>,
+}
+
+impl<Tz: TimeZone> ClosedPairwiseDateIterator<Tz> {
+    /// see `ClosedDateIterator::exact_len`
+    fn exact_len(&self) -> usize {
+        let grid = &self.iter.iter;
+        count_in_range(grid.iterations,
+                        &self.to,
+                        grid.duration.approx_nanos(),
+                        |n| pairwise_at(grid, n).map(|(start, _)| start))
+    }
+}
+
+/// `(grid.at(n), grid.at(n + 1))`, ordered chronologically. Same pairing
+/// `OpenEndedPairwiseDateIterator::next()` yields, but for an arbitrary `n` instead
+/// of just the current step.
+fn pairwise_at<Tz: TimeZone>(grid: &OpenEndedDateIterator<Tz>,
+                             n: i32)
+                             -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+    let start = try_opt!(grid.at(n));
+    let next = try_opt!(grid.at(n + 1));
+    Some(if start <= next { (start, next) } else { (next, start) })
+}
+
+/// the number of `n >= from_n` for which `at(n)` is `Some` and less than `to`,
+/// found by jumping to an estimate based on `step_nanos` (the approximate length of
+/// one step of the underlying `CalendarDuration`) and then walking a short distance
+/// to the exact boundary, rather than a full linear scan from `from_n`.
+fn count_in_range<Tz, F>(from_n: i32, to: &DateTime<Tz>, step_nanos: i64, at: F) -> usize
+    where Tz: TimeZone,
+          F: Fn(i32) -> Option<DateTime<Tz>>
+{
+    let from_dt = match at(from_n) {
+        Some(dt) => dt,
+        None => return 0,
+    };
+    if from_dt >= *to {
+        return 0;
+    }
+
+    let span_nanos = to.clone()
+        .signed_duration_since(from_dt)
+        .num_nanoseconds()
+        .unwrap_or(i64::max_value());
+    let estimate = if step_nanos != 0 {
+        span_nanos / step_nanos.abs()
+    } else {
+        0
+    };
+    let n = (from_n as i64 + estimate.max(0)).min(i32::max_value() as i64);
+    let mut n = if n < from_n as i64 { from_n } else { n as i32 };
+
+    // the estimate treats months/years as their average length, so it can land
+    // slightly past the true boundary; back off until we're in range again
+    while n > from_n && !at(n).map_or(false, |dt| dt < *to) {
+        n -= 1;
+    }
+
+    let mut last = n;
+    while at(last + 1).map_or(false, |dt| dt < *to) {
+        last += 1;
+    }
+
+    (last as i64 - from_n as i64 + 1) as usize
 }
 
 /// returns an open ended `Iterator`, that will first yield `dt`
@@ -79,18 +214,37 @@ pub fn date_iterator_from<Tz: TimeZone, D: Into<CalendarDuration>>(dt: DateTime<
     }
 }
 
-pub fn date_iterator_to<Tz: TimeZone, Iter: Iterator<Item = DateTime<Tz>>>
-    (iter: Iter,
-     to: DateTime<Tz>)
-     -> ClosedDateIterator<Tz, Iter> {
-    ClosedDateIterator { iter: iter, to: to }
+/// returns an open ended `Iterator` that walks *backward* from `dt`, first yielding `dt`,
+/// then `dt - duration`, `dt - 2*duration`, etc.
+///
+/// This is the `date_iterator_from` analogue of `earlier()`/`and_earlier()` in other date
+/// libraries: it is simply `date_iterator_from` with a negated step, so `.to(...)` and
+/// `.pairwise()` keep working unchanged.
+pub fn date_iterator_before<Tz: TimeZone, D: Into<CalendarDuration>>(dt: DateTime<Tz>,
+                                                                     duration: D)
+                                                                     -> OpenEndedDateIterator<Tz> {
+    date_iterator_from(dt, -duration.into())
+}
+
+pub fn date_iterator_to<Tz: TimeZone>(iter: OpenEndedDateIterator<Tz>,
+                                      to: DateTime<Tz>)
+                                      -> ClosedDateIterator<Tz> {
+    //a zero-duration step never reaches `to`, so the iterator would be infinite
+    //while still claiming `ExactSizeIterator`; reject it here rather than at
+    //`len()`/`size_hint()` time, which is too late to stop `collect()` from looping forever
+    assert!(!iter.duration.is_zero(),
+            "a zero-duration date iterator never reaches `to`; it has no well-defined size");
+    ClosedDateIterator {
+        iter: iter,
+        to: to,
+        back: None,
+    }
 }
 
-pub fn date_iterator_from_to<Tz: TimeZone, D: Into<CalendarDuration>>
-    (from: DateTime<Tz>,
-     duration: D,
-     to: DateTime<Tz>)
-     -> ClosedDateIterator<Tz, OpenEndedDateIterator<Tz>> {
+pub fn date_iterator_from_to<Tz: TimeZone, D: Into<CalendarDuration>>(from: DateTime<Tz>,
+                                                                      duration: D,
+                                                                      to: DateTime<Tz>)
+                                                                      -> ClosedDateIterator<Tz> {
 
     date_iterator_from(from, duration).to(to)
 }
@@ -111,11 +265,17 @@ impl<Tz: TimeZone> Iterator for OpenEndedPairwiseDateIterator<Tz> {
     fn next(&mut self) -> Option<Self::Item> {
         self.iter
             .next()
-            .and_then(|start| Some((start, try_opt!(self.iter.current()))))
+            .and_then(|start| {
+                let next = try_opt!(self.iter.current());
+                //order the pair chronologically, so a `date_iterator_before` (negative
+                //duration) pairwise iteration still yields non-overlapping (earlier, later)
+                //slices instead of (later, earlier) ones.
+                Some(if start <= next { (start, next) } else { (next, start) })
+            })
     }
 }
 
-impl<Tz: TimeZone, Iter: Iterator<Item = DateTime<Tz>>> Iterator for ClosedDateIterator<Tz, Iter> {
+impl<Tz: TimeZone> Iterator for ClosedDateIterator<Tz> {
     type Item = DateTime<Tz>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -124,6 +284,36 @@ impl<Tz: TimeZone, Iter: Iterator<Item = DateTime<Tz>>> Iterator for ClosedDateI
             .next()
             .and_then(|dt| if dt < self.to { Some(dt) } else { None })
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.exact_len();
+        (n, Some(n))
+    }
+}
+
+impl<Tz: TimeZone> DoubleEndedIterator for ClosedDateIterator<Tz> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back.is_none() {
+            self.back = Some(try_opt!(last_in_range(&self.iter, &self.to, self.iter.iterations)));
+        }
+        let n = try_opt!(self.back);
+        //the two ends have met: nothing left in between for either side to yield
+        if n < self.iter.iterations {
+            return None;
+        }
+        let value = try_opt!(self.iter.at(n));
+        //shrink the upper bound so a subsequent `next()` stops here too, instead of
+        //re-yielding a date `next_back()` already handed out
+        self.to = value.clone();
+        self.back = Some(n - 1);
+        Some(value)
+    }
+}
+
+impl<Tz: TimeZone> ExactSizeIterator for ClosedDateIterator<Tz> {
+    fn len(&self) -> usize {
+        self.exact_len()
+    }
 }
 
 impl<Tz: TimeZone> Iterator for ClosedPairwiseDateIterator<Tz> {
@@ -135,6 +325,37 @@ impl<Tz: TimeZone> Iterator for ClosedPairwiseDateIterator<Tz> {
             .next()
             .and_then(|dts| if dts.0 < self.to { Some(dts) } else { None })
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.exact_len();
+        (n, Some(n))
+    }
+}
+
+impl<Tz: TimeZone> DoubleEndedIterator for ClosedPairwiseDateIterator<Tz> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let inner = &self.iter.iter;
+        if self.back.is_none() {
+            self.back = Some(try_opt!(last_in_range(inner, &self.to, inner.iterations)));
+        }
+        let n = try_opt!(self.back);
+        if n < inner.iterations {
+            return None;
+        }
+        let start = try_opt!(inner.at(n));
+        let next = try_opt!(inner.at(n + 1));
+        //shrink the upper bound so a subsequent `next()` stops here too
+        self.to = start.clone();
+        self.back = Some(n - 1);
+        Some(if start <= next { (start, next) } else { (next, start) })
+    }
+}
+
+impl<Tz: TimeZone> ExactSizeIterator for ClosedPairwiseDateIterator<Tz> {
+    /// see `ClosedDateIterator::len`
+    fn len(&self) -> usize {
+        self.exact_len()
+    }
 }
 
 #[cfg(test)]
@@ -142,7 +363,7 @@ mod tests {
 
     use std::str::FromStr;
 
-    use chrono::Utc;
+    use chrono::{Utc, Weekday};
 
     use super::*;
 
@@ -217,4 +438,186 @@ mod tests {
                        .collect::<Vec<_>>());
     }
 
+    #[test]
+    pub fn test_closed_date_iterator_len() {
+        let from_str = "1996-12-25T16:39:57.123Z";
+        let from_dt = DateTime::<Utc>::from_str(from_str).unwrap();
+
+        let to_str = "2006-03-31T16:51:57.123Z";
+        let to_dt = DateTime::<Utc>::from_str(to_str).unwrap();
+
+        let duration = CalendarDuration::years(3) + CalendarDuration::months(1) +
+                       CalendarDuration::days(2) +
+                       CalendarDuration::minutes(4);
+
+        let mut iter = date_iterator_from(from_dt, duration).to(to_dt);
+        assert_eq!(3, iter.len());
+
+        iter.next();
+        assert_eq!(2, iter.len());
+    }
+
+    #[test]
+    pub fn test_closed_pairwise_date_iterator_len() {
+        let from_str = "1996-12-25T16:39:57.123Z";
+        let from_dt = DateTime::<Utc>::from_str(from_str).unwrap();
+
+        let to_str = "2006-03-31T16:51:57.123Z";
+        let to_dt = DateTime::<Utc>::from_str(to_str).unwrap();
+
+        let duration = CalendarDuration::years(3) + CalendarDuration::months(1) +
+                       CalendarDuration::days(2) +
+                       CalendarDuration::minutes(4);
+
+        let iter = date_iterator_from(from_dt, duration)
+            .to(to_dt)
+            .pairwise();
+        assert_eq!(3, iter.len());
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_date_iterator_to_panics_on_zero_duration() {
+        let from_str = "1996-12-25T16:39:57.123Z";
+        let from_dt = DateTime::<Utc>::from_str(from_str).unwrap();
+
+        let to_str = "2006-03-31T16:51:57.123Z";
+        let to_dt = DateTime::<Utc>::from_str(to_str).unwrap();
+
+        //a zero-duration step never reaches `to`, so this would otherwise be an
+        //infinite iterator wrongly claiming `ExactSizeIterator`; `.to()` rejects it
+        //immediately rather than only panicking later when `.len()` is called
+        date_iterator_from(from_dt, CalendarDuration::zero()).to(to_dt);
+    }
+
+    #[test]
+    pub fn test_date_iterator_before() {
+        let input = "2006-03-31T16:51:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let duration = CalendarDuration::years(3) + CalendarDuration::months(1);
+
+        let iter = date_iterator_before(dt, duration);
+        //note these are computed as `from + n * duration` (see `OpenEndedDateIterator::at`),
+        //not by repeatedly subtracting and re-clamping from the previous result: the 2nd step
+        //is `2006-03-31 - 6y2m` in one shot, not `(2006-03-31 - 3y1m) - 3y1m`, so it lands on
+        //2000-01-31 rather than the 28th. The later steps cross a year boundary backward
+        //(`month0` goes negative internally before being re-normalized), which is the case
+        //that used to panic.
+        let expected = vec!["2006-03-31T16:51:57.123Z",
+                            "2003-02-28T16:51:57.123Z",
+                            "2000-01-31T16:51:57.123Z",
+                            "1996-12-31T16:51:57.123Z",
+                            "1993-11-30T16:51:57.123Z"];
+
+        assert_eq!(expected,
+                   iter.take(5)
+                       .map(|d| format!("{:?}", d))
+                       .collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_open_ended_iterator_terminates_on_overflow() {
+        let input = "1996-12-25T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        //with a duration this large, multiplying by `iterations` overflows almost
+        //immediately; the iterator should terminate rather than panic
+        let duration = CalendarDuration::years(i32::max_value());
+        let mut iter = date_iterator_from(dt, duration);
+
+        assert_eq!(Some(dt), iter.next());
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    pub fn test_aligned_to_snaps_to_next_occurrence_of_weekday() {
+        //1996-12-25 was a Wednesday
+        let input = "1996-12-25T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let iter = date_iterator_from(dt, CalendarDuration::weeks(2)).aligned_to(Weekday::Mon);
+        let expected = vec!["1996-12-30T16:39:57.123Z",
+                            "1997-01-13T16:39:57.123Z",
+                            "1997-01-27T16:39:57.123Z"];
+
+        assert_eq!(expected,
+                   iter.take(3)
+                       .map(|d| format!("{:?}", d))
+                       .collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_aligned_to_drifts_off_weekday_for_a_duration_with_a_non_week_part() {
+        //1996-12-25 was a Wednesday
+        let input = "1996-12-25T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        //stepping by the whole duration (not just its week part) means the extra
+        //day shifts the weekday by one on every step after the snap
+        let duration = CalendarDuration::weeks(2) + CalendarDuration::days(1);
+        let iter = date_iterator_from(dt, duration).aligned_to(Weekday::Mon);
+
+        let weekdays = iter.take(3).map(|d| d.weekday()).collect::<Vec<_>>();
+        assert_eq!(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed], weekdays);
+    }
+
+    #[test]
+    pub fn test_aligned_to_is_a_no_op_when_already_on_the_weekday() {
+        //1996-12-30 was already a Monday
+        let input = "1996-12-30T16:39:57.123Z";
+        let dt = DateTime::<Utc>::from_str(input).unwrap();
+
+        let iter = date_iterator_from(dt, CalendarDuration::weeks(1)).aligned_to(Weekday::Mon);
+
+        assert_eq!(Some(dt), iter.take(1).next());
+    }
+
+    #[test]
+    pub fn test_closed_date_iterator_next_back() {
+        let from_str = "1996-12-25T16:39:57.123Z";
+        let from_dt = DateTime::<Utc>::from_str(from_str).unwrap();
+
+        let to_str = "2006-03-31T16:51:57.123Z";
+        let to_dt = DateTime::<Utc>::from_str(to_str).unwrap();
+
+        let duration = CalendarDuration::years(3) + CalendarDuration::months(1) +
+                       CalendarDuration::days(2) +
+                       CalendarDuration::minutes(4);
+
+        let mut iter = date_iterator_from(from_dt, duration).to(to_dt);
+        let last = iter.next_back().map(|d| format!("{:?}", d));
+        let first = iter.next().map(|d| format!("{:?}", d));
+        let middle = iter.next().map(|d| format!("{:?}", d));
+
+        assert_eq!(Some("2003-02-28T16:47:57.123Z".to_owned()), last);
+        assert_eq!(Some("1996-12-25T16:39:57.123Z".to_owned()), first);
+        assert_eq!(Some("2000-01-27T16:43:57.123Z".to_owned()), middle);
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.next_back());
+    }
+
+    #[test]
+    pub fn test_closed_pairwise_date_iterator_next_back() {
+        let from_str = "1996-12-25T16:39:57.123Z";
+        let from_dt = DateTime::<Utc>::from_str(from_str).unwrap();
+
+        let to_str = "2006-03-31T16:51:57.123Z";
+        let to_dt = DateTime::<Utc>::from_str(to_str).unwrap();
+
+        let duration = CalendarDuration::years(3) + CalendarDuration::months(1) +
+                       CalendarDuration::days(2) +
+                       CalendarDuration::minutes(4);
+
+        let mut iter = date_iterator_from(from_dt, duration)
+            .to(to_dt)
+            .pairwise();
+        let last = iter.next_back()
+            .map(|d| format!("{:?} to {:?}", d.0, d.1));
+
+        assert_eq!(Some("2003-02-28T16:47:57.123Z to 2006-03-31T16:51:57.123Z".to_owned()),
+                   last);
+    }
+
 }